@@ -3,12 +3,40 @@ use std::{cmp, net::SocketAddr, time::Duration, time::Instant};
 use super::pacing::Pacer;
 use crate::{congestion, MIN_MTU, TIMER_GRANULARITY};
 
+/// The largest MTU we'll search up to by default
+///
+/// This comfortably covers the common case of a path that can carry standard 1500-byte Ethernet
+/// frames, while staying well short of jumbo frame sizes that are rare enough not to assume.
+const MAX_MTU_CEILING: u16 = 1452;
+
+/// How long we wait after a search completes, or after a black hole is detected, before a fresh
+/// search may be re-armed
+const MTU_DISCOVERY_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Number of outgoing ECT(0)-marked packets we'll send without any feedback before giving up
+const ECN_TESTING_WINDOW: u32 = 10;
+
+/// Largest multiple of the observed minimum RTT we'll persist in a resumption token
+const RESUMPTION_RTT_MIN_MULTIPLIER: u32 = 3;
+
+/// Absolute ceiling on the smoothed RTT we'll persist in a resumption token
+const RESUMPTION_RTT_MAX: Duration = Duration::from_millis(250);
+
+/// Bounds on the `max_ack_delay` we'll request of the peer via ACK_FREQUENCY
+const MIN_REQUESTED_ACK_DELAY: Duration = Duration::from_millis(1);
+const MAX_REQUESTED_ACK_DELAY: Duration = Duration::from_millis(25);
+
 /// Description of a particular network path
 pub struct PathData {
     pub remote: SocketAddr,
     pub rtt: RttEstimator,
     /// Whether we're enabling ECN on outgoing packets
+    ///
+    /// Kept in sync with `ecn.is_capable()`; exposed separately so the send path doesn't need to
+    /// know about ECN validation to decide whether to mark outgoing datagrams.
     pub sending_ecn: bool,
+    /// ECN validation state for this path (RFC 9000 §13.4)
+    ecn: EcnValidation,
     /// Congestion controller state
     pub congestion: Box<dyn congestion::Controller>,
     /// Pacing state
@@ -24,7 +52,19 @@ pub struct PathData {
     pub total_sent: u64,
     /// Total size of all UDP datagrams received on this path
     pub total_recvd: u64,
-    pub mtu: u16,
+    /// Packetization Layer Path MTU Discovery state for this path
+    pub mtud: Mtud,
+    /// Most recently computed desired ACK rate, sent to the peer via ACK_FREQUENCY
+    pub ack_rate: AckRate,
+    /// Optional qlog sink for this path's RTT and congestion telemetry
+    ///
+    /// `None` in the common case, making every qlog-related call on this path a no-op: no
+    /// allocation, no formatting, not even the bookkeeping needed to dedup future events.
+    qlog: Option<Box<dyn QlogSink>>,
+    /// Congestion window last reported to the qlog sink, for deduplication
+    last_emitted_congestion_window: Option<u64>,
+    /// Bytes in flight last reported to the qlog sink, for deduplication
+    last_emitted_bytes_in_flight: Option<u64>,
 }
 
 impl PathData {
@@ -35,44 +75,496 @@ impl PathData {
         now: Instant,
         validated: bool,
     ) -> Self {
+        Self::with_rtt(
+            remote,
+            RttEstimator::new(initial_rtt),
+            congestion,
+            now,
+            validated,
+        )
+    }
+
+    fn with_rtt(
+        remote: SocketAddr,
+        rtt: RttEstimator,
+        congestion: Box<dyn congestion::Controller>,
+        now: Instant,
+        validated: bool,
+    ) -> Self {
+        let ack_rate = AckRate::compute(rtt.get(), congestion.initial_window(), MIN_MTU);
         PathData {
             remote,
-            rtt: RttEstimator::new(initial_rtt),
+            pacing: Pacer::new(rtt.get(), congestion.initial_window(), MIN_MTU, now),
+            rtt,
             sending_ecn: true,
-            pacing: Pacer::new(initial_rtt, congestion.initial_window(), MIN_MTU, now),
+            ecn: EcnValidation::new(),
             congestion,
             challenge: None,
             challenge_pending: false,
             validated,
             total_sent: 0,
             total_recvd: 0,
-            mtu: MIN_MTU,
+            mtud: Mtud::new(MAX_MTU_CEILING, now),
+            ack_rate,
+            qlog: None,
+            last_emitted_congestion_window: None,
+            last_emitted_bytes_in_flight: None,
         }
     }
 
     pub fn from_previous(remote: SocketAddr, prev: &PathData, now: Instant) -> Self {
         let congestion = prev.congestion.clone_box();
         let smoothed_rtt = prev.rtt.get();
+        let ack_rate = AckRate::compute(smoothed_rtt, congestion.window(), MIN_MTU);
         PathData {
             remote,
             rtt: prev.rtt,
-            pacing: Pacer::new(smoothed_rtt, congestion.window(), prev.mtu, now),
+            pacing: Pacer::new(smoothed_rtt, congestion.window(), MIN_MTU, now),
+            // A new path must be re-validated from scratch: the old path's ECN result says
+            // nothing about whether this one mangles ECN codepoints.
             sending_ecn: true,
+            ecn: EcnValidation::new(),
             congestion,
             challenge: None,
             challenge_pending: false,
             validated: false,
             total_sent: 0,
             total_recvd: 0,
-            mtu: prev.mtu,
+            // A new path may behave completely differently from the old one, so MTU discovery
+            // must start over from scratch rather than trusting the previous path's result.
+            mtud: Mtud::new(prev.mtud.ceiling(), now),
+            ack_rate,
+            // Re-attached by the connection after migration, if qlog is in use.
+            qlog: None,
+            last_emitted_congestion_window: None,
+            last_emitted_bytes_in_flight: None,
         }
     }
 
+    /// Construct the initial path for a connection resuming with a persisted RTT estimate
+    ///
+    /// `resumed_rtt` is expected to have come from [`RttEstimator::to_resumption`] and is passed
+    /// through [`RttEstimator::from_resumption`], which clamps it to `RESUMPTION_RTT_MAX`; the
+    /// value is not otherwise checked.
+    pub fn from_resumption(
+        remote: SocketAddr,
+        resumed_rtt: Duration,
+        congestion: Box<dyn congestion::Controller>,
+        now: Instant,
+        validated: bool,
+    ) -> Self {
+        Self::with_rtt(
+            remote,
+            RttEstimator::from_resumption(resumed_rtt),
+            congestion,
+            now,
+            validated,
+        )
+    }
+
     /// Indicates whether we're a server that hasn't validated the peer's address and hasn't
     /// received enough data from the peer to permit sending `bytes_to_send` additional bytes
     pub fn anti_amplification_blocked(&self, bytes_to_send: u64) -> bool {
         !self.validated && self.total_recvd * 3 < self.total_sent + bytes_to_send
     }
+
+    /// Current confirmed MTU for this path
+    pub fn current_mtu(&self) -> u16 {
+        self.mtud.current_mtu()
+    }
+
+    /// Call once per outgoing ECT(0)-marked packet
+    pub fn on_ecn_sent(&mut self) {
+        self.ecn.on_packet_sent();
+    }
+
+    /// Call when an ACK newly acknowledges `newly_acked` packets we sent ECT(0)-marked, reporting
+    /// the peer's total ECT(0) and CE counts as echoed in the ACK frame's ECN section
+    pub fn on_ecn_ack(&mut self, newly_acked: u64, reported_ect0: u64, reported_ce: u64) {
+        self.ecn.on_ack(newly_acked, reported_ect0, reported_ce);
+        self.sending_ecn = self.ecn.is_capable();
+    }
+
+    /// Call periodically to abandon ECN if the initial testing window elapses with no feedback
+    pub fn check_ecn_testing_window(&mut self) {
+        self.ecn.on_testing_window_elapsed();
+        self.sending_ecn = self.ecn.is_capable();
+    }
+
+    /// Recompute the desired ACK rate from the current RTT and congestion window
+    ///
+    /// Must be called whenever either input changes, so that `ack_rate` stays current for the
+    /// next ACK_FREQUENCY frame we send.
+    pub fn update_ack_rate(&mut self) {
+        self.ack_rate = AckRate::compute(
+            self.rtt.get(),
+            self.congestion.window(),
+            self.mtud.current_mtu(),
+        );
+    }
+
+    /// Attach a qlog sink to this path, enabling RTT and congestion telemetry
+    pub fn set_qlog(&mut self, sink: Box<dyn QlogSink>) {
+        self.qlog = Some(sink);
+    }
+
+    /// Record an RTT sample: updates the estimator, the derived ACK rate, and (if a qlog sink is
+    /// attached) emits a `metrics_updated` event carrying whichever fields actually changed
+    pub fn record_rtt_sample(&mut self, now: Instant, ack_delay: Duration, rtt: Duration) {
+        self.rtt.update(now, ack_delay, rtt);
+        self.update_ack_rate();
+        let Self { rtt, qlog, .. } = self;
+        if let Some(sink) = qlog.as_deref_mut() {
+            if let Some(update) = rtt.qlog_update() {
+                sink.emit_metrics_updated(update);
+            }
+        }
+    }
+
+    /// Notify the qlog sink, if any, of the current congestion window and bytes in flight
+    ///
+    /// Only the fields that changed since the last call are included in the emitted event.
+    pub fn record_congestion_metrics(&mut self, bytes_in_flight: u64) {
+        let Self {
+            congestion,
+            qlog,
+            last_emitted_congestion_window,
+            last_emitted_bytes_in_flight,
+            ..
+        } = self;
+        if let Some(sink) = qlog.as_deref_mut() {
+            let window = congestion.window();
+            let congestion_window =
+                (Some(window) != *last_emitted_congestion_window).then_some(window);
+            let bytes_in_flight_field =
+                (Some(bytes_in_flight) != *last_emitted_bytes_in_flight).then_some(bytes_in_flight);
+            if congestion_window.is_some() || bytes_in_flight_field.is_some() {
+                *last_emitted_congestion_window = Some(window);
+                *last_emitted_bytes_in_flight = Some(bytes_in_flight);
+                sink.emit_congestion_updated(CongestionMetricsUpdate {
+                    congestion_window,
+                    bytes_in_flight: bytes_in_flight_field,
+                });
+            }
+        }
+    }
+
+    /// Notify the qlog sink, if any, that we responded to a detected loss
+    pub fn record_recovery_event(&mut self, bytes_lost: u64) {
+        if let Some(sink) = self.qlog.as_deref_mut() {
+            sink.emit_recovery(RecoveryEvent {
+                congestion_window: self.congestion.window(),
+                bytes_lost,
+            });
+        }
+    }
+}
+
+/// Sink for qlog telemetry emitted by [`PathData`] and [`RttEstimator`]
+///
+/// Implemented by quinn's qlog integration. Kept as a trait object behind `Option` so that a
+/// connection with no qlog sink configured pays nothing for this instrumentation.
+pub trait QlogSink {
+    fn emit_metrics_updated(&mut self, update: RttMetricsUpdate);
+    fn emit_congestion_updated(&mut self, update: CongestionMetricsUpdate);
+    fn emit_recovery(&mut self, event: RecoveryEvent);
+}
+
+/// RTT fields to report in a qlog `metrics_updated` event, populated only where the value
+/// changed since the last event
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RttMetricsUpdate {
+    pub smoothed_rtt: Option<Duration>,
+    pub latest_rtt: Option<Duration>,
+    pub min_rtt: Option<Duration>,
+    pub rtt_variance: Option<Duration>,
+}
+
+/// Congestion fields to report in a qlog `metrics_updated` event, populated only where the value
+/// changed since the last event
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CongestionMetricsUpdate {
+    pub congestion_window: Option<u64>,
+    pub bytes_in_flight: Option<u64>,
+}
+
+/// A qlog `recovery` event: our congestion response to a detected loss
+#[derive(Copy, Clone, Debug)]
+pub struct RecoveryEvent {
+    pub congestion_window: u64,
+    pub bytes_lost: u64,
+}
+
+/// State of ECN validation for a path (RFC 9000 §13.4)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum EcnState {
+    /// Sending ECT(0) on an initial run of packets to see whether the path echoes it back
+    Testing,
+    /// ECT(0) marks have been sent and correctly echoed; safe to keep marking
+    Capable,
+    /// Validation failed; ECN is disabled for the remainder of this path's lifetime
+    Failed,
+}
+
+/// Validates that ECN marks we send actually survive the path and get echoed back correctly
+///
+/// We mark outgoing packets ECT(0) and compare the ECT(0)+CE counts the peer echoes in its ACK
+/// frames against how many marked packets it has newly acknowledged. Any discrepancy - a
+/// codepoint disappearing, being remapped, or under-counted - means a middlebox is mangling ECN,
+/// so we stop marking permanently. No feedback at all within the initial testing window is
+/// treated the same way.
+#[derive(Copy, Clone, Debug)]
+struct EcnValidation {
+    state: EcnState,
+    /// Outgoing ECT(0)-marked packets sent so far, used to size the testing window
+    sent: u32,
+    /// Total ECT(0) + CE marks we've sent that have since been acknowledged
+    acked: u64,
+    /// Highest ECT(0)+CE count the peer has reported to us so far
+    max_reported: u64,
+}
+
+impl EcnValidation {
+    fn new() -> Self {
+        Self {
+            state: EcnState::Testing,
+            sent: 0,
+            acked: 0,
+            max_reported: 0,
+        }
+    }
+
+    /// Whether we should currently be marking outgoing packets ECT(0)
+    fn is_capable(&self) -> bool {
+        !matches!(self.state, EcnState::Failed)
+    }
+
+    fn on_packet_sent(&mut self) {
+        if self.state != EcnState::Failed {
+            self.sent += 1;
+        }
+    }
+
+    fn on_ack(&mut self, newly_acked: u64, reported_ect0: u64, reported_ce: u64) {
+        if self.state == EcnState::Failed || newly_acked == 0 {
+            return;
+        }
+        let reported = reported_ect0 + reported_ce;
+        if reported < self.max_reported || reported < self.acked + newly_acked {
+            // Counts didn't add up, or a codepoint was erased/remapped along the way.
+            self.state = EcnState::Failed;
+            return;
+        }
+        self.max_reported = reported;
+        self.acked += newly_acked;
+        self.state = EcnState::Capable;
+    }
+
+    fn on_testing_window_elapsed(&mut self) {
+        if self.state == EcnState::Testing
+            && self.sent >= ECN_TESTING_WINDOW
+            && self.max_reported == 0
+        {
+            self.state = EcnState::Failed;
+        }
+    }
+}
+
+/// State of the DPLPMTUD (RFC 8899) search for a single path
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum MtudState {
+    /// No search is running yet, either initially or after `maybe_rearm`; the next
+    /// `poll_transmit` starts one from the current confirmed MTU
+    Base,
+    /// Binary-searching upward from `current_mtu` toward `ceiling`
+    Searching,
+    /// The search converged; `current_mtu` is the largest size known to work
+    SearchComplete,
+    /// A black hole was detected; `current_mtu` was reset and we're waiting to retry
+    Error,
+}
+
+/// Packetization Layer Path MTU Discovery (RFC 8899) state machine
+///
+/// Probes are padded datagrams sent with their own packet number. They're deliberately excluded
+/// from congestion-controller in-flight accounting and don't produce RTT samples or a congestion
+/// response on loss: losing a probe only tells us the *candidate* size doesn't work, not that the
+/// path is congested.
+#[derive(Copy, Clone, Debug)]
+pub struct Mtud {
+    state: MtudState,
+    /// Largest MTU confirmed to be usable on this path
+    current_mtu: u16,
+    /// Upper bound of the current binary search; never probed above this
+    search_high: u16,
+    /// Configured ceiling; the search never proposes a candidate above it
+    ceiling: u16,
+    /// Packet number of the currently outstanding probe, if any
+    probe_pn: Option<u64>,
+    /// Size in bytes of the currently outstanding probe
+    probe_size: u16,
+    /// Consecutive full-size packet losses observed since the last successful probe, used for
+    /// black-hole detection
+    black_hole_losses: u32,
+    /// When to next allow a new search to be armed (after an idle interval or an error backoff)
+    next_search_at: Instant,
+}
+
+impl Mtud {
+    fn new(ceiling: u16, now: Instant) -> Self {
+        let ceiling = cmp::max(ceiling, MIN_MTU);
+        Mtud {
+            state: MtudState::Base,
+            current_mtu: MIN_MTU,
+            search_high: ceiling,
+            ceiling,
+            probe_pn: None,
+            probe_size: 0,
+            black_hole_losses: 0,
+            next_search_at: now,
+        }
+    }
+
+    /// Largest MTU confirmed to work on this path so far
+    pub fn current_mtu(&self) -> u16 {
+        self.current_mtu
+    }
+
+    /// Configured ceiling for this path's search
+    pub fn ceiling(&self) -> u16 {
+        self.ceiling
+    }
+
+    /// Whether a probe is currently outstanding
+    pub fn is_probing(&self) -> bool {
+        self.probe_pn.is_some()
+    }
+
+    /// Size and packet number of a new probe to send, if one should be sent now
+    ///
+    /// The caller is responsible for padding the datagram to the returned size, assigning it the
+    /// returned packet number, and keeping it out of congestion-controller in-flight accounting.
+    pub fn poll_transmit(&mut self, now: Instant, next_pn: u64) -> Option<(u16, u64)> {
+        if self.probe_pn.is_some() {
+            // Only one probe outstanding at a time
+            return None;
+        }
+        match self.state {
+            MtudState::Base | MtudState::Searching => {
+                if now < self.next_search_at {
+                    return None;
+                }
+                if self.search_high <= self.current_mtu {
+                    self.state = MtudState::SearchComplete;
+                    // Without this, a `Base` state reached via `maybe_rearm` (rather than via
+                    // `on_probe_acked` reaching the ceiling) would leave `next_search_at` stale,
+                    // so the very next `maybe_rearm` call would immediately flip back to `Base`
+                    // again regardless of how much time has actually passed.
+                    self.next_search_at = now + MTU_DISCOVERY_INTERVAL;
+                    return None;
+                }
+                self.state = MtudState::Searching;
+                let candidate = self.current_mtu + (self.search_high - self.current_mtu) / 2;
+                let candidate = cmp::max(candidate, self.current_mtu + 1);
+                self.probe_pn = Some(next_pn);
+                self.probe_size = candidate;
+                Some((candidate, next_pn))
+            }
+            MtudState::SearchComplete | MtudState::Error => None,
+        }
+    }
+
+    /// Record that the outstanding probe, if it was `pn`, was acknowledged
+    pub fn on_probe_acked(&mut self, pn: u64, now: Instant) {
+        if self.probe_pn != Some(pn) {
+            return;
+        }
+        self.probe_pn = None;
+        self.current_mtu = self.probe_size;
+        self.black_hole_losses = 0;
+        if self.current_mtu >= self.search_high {
+            self.state = MtudState::SearchComplete;
+            // Without this, `next_search_at` is left at whatever it was when the search started
+            // (already in the past), so the very next `maybe_rearm` call would immediately kick
+            // us back into `Base` and restart probing instead of waiting out the idle interval.
+            self.next_search_at = now + MTU_DISCOVERY_INTERVAL;
+        } else {
+            self.state = MtudState::Searching;
+        }
+    }
+
+    /// Record that the outstanding probe, if it was `pn`, was lost
+    ///
+    /// Probe loss narrows the search window; it must never be treated as a congestion signal.
+    pub fn on_probe_lost(&mut self, pn: u64, now: Instant) {
+        if self.probe_pn != Some(pn) {
+            return;
+        }
+        self.probe_pn = None;
+        self.search_high = self.probe_size.saturating_sub(1).max(self.current_mtu);
+        if self.search_high <= self.current_mtu {
+            self.state = MtudState::SearchComplete;
+            self.next_search_at = now + MTU_DISCOVERY_INTERVAL;
+        }
+    }
+
+    /// Called when a persistent run of losses of full-size (non-probe) packets suggests a
+    /// black hole has opened up on the path; drops back to the safe minimum and holds off probing
+    /// until `maybe_rearm` is called at least `MTU_DISCOVERY_INTERVAL` later
+    pub fn black_hole_detected(&mut self, now: Instant) {
+        self.state = MtudState::Error;
+        self.current_mtu = MIN_MTU;
+        self.search_high = self.ceiling;
+        self.probe_pn = None;
+        self.black_hole_losses = 0;
+        self.next_search_at = now + MTU_DISCOVERY_INTERVAL;
+    }
+
+    /// Feed in the outcome of a non-probe packet at the current confirmed MTU, for black-hole
+    /// detection; returns `true` if a black hole was just detected
+    pub fn on_non_probe_lost(&mut self, now: Instant, persistent_loss_threshold: u32) -> bool {
+        self.black_hole_losses += 1;
+        if self.black_hole_losses >= persistent_loss_threshold {
+            self.black_hole_detected(now);
+            return true;
+        }
+        false
+    }
+
+    pub fn on_non_probe_acked(&mut self) {
+        self.black_hole_losses = 0;
+    }
+
+    /// Re-arm the search after an idle period or following black-hole recovery
+    pub fn maybe_rearm(&mut self, now: Instant) {
+        if now < self.next_search_at {
+            return;
+        }
+        match self.state {
+            MtudState::SearchComplete | MtudState::Error => {
+                self.state = MtudState::Base;
+                self.search_high = self.ceiling;
+            }
+            MtudState::Base | MtudState::Searching => {}
+        }
+    }
+}
+
+/// The peer's advertised maximum ACK delay, negotiated via the QUIC ACK Frequency extension
+///
+/// Until negotiated, `ack_delay` is used as reported and application-space PTO doesn't add any
+/// extra delay on top of `pto_base`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PeerAckDelay(Option<Duration>);
+
+impl PeerAckDelay {
+    pub fn get(&self) -> Option<Duration> {
+        self.0
+    }
+
+    pub fn set(&mut self, max_ack_delay: Duration) {
+        self.0 = Some(max_ack_delay);
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -85,6 +577,25 @@ pub struct RttEstimator {
     var: Duration,
     /// The minimum RTT seen in the connection, ignoring ack delay.
     min: Duration,
+    /// The peer's advertised maximum ack delay, if negotiated via ACK Frequency
+    max_ack_delay: PeerAckDelay,
+    /// Snapshot of `(latest, smoothed, min, var)` as of the last qlog emission, for deduplication
+    last_emitted: Option<(Duration, Option<Duration>, Duration, Duration)>,
+    /// When the first genuine RTT sample was recorded, if ever
+    ///
+    /// Distinguishes a real sample from the `initial_rtt` guess seeded by `new`, so that a
+    /// single unusually high first sample can't permanently poison `min`, and so `pto_base` can
+    /// back off further while every field is still just a guess.
+    first_sample_time: Option<Instant>,
+    /// Whether this estimator was seeded from [`to_resumption`], rather than a blind guess
+    ///
+    /// Unlike an unsampled blind guess, a trusted seed is real history from a previous
+    /// connection to the same peer, so it shouldn't be penalized by [`is_guessed`]'s extra
+    /// backoff even before the first sample of *this* connection arrives.
+    ///
+    /// [`to_resumption`]: Self::to_resumption
+    /// [`is_guessed`]: Self::is_guessed
+    trusted_seed: bool,
 }
 
 impl RttEstimator {
@@ -94,47 +605,477 @@ impl RttEstimator {
             smoothed: None,
             var: initial_rtt / 2,
             min: initial_rtt,
+            max_ack_delay: PeerAckDelay::default(),
+            last_emitted: None,
+            first_sample_time: None,
+            trusted_seed: false,
         }
     }
 
-    pub fn update(&mut self, ack_delay: Duration, rtt: Duration) {
+    /// Whether every RTT/variance field is still an unconfirmed guess, with no real sample from
+    /// this connection and no trusted seed from a previous one
+    pub fn is_guessed(&self) -> bool {
+        self.first_sample_time.is_none() && !self.trusted_seed
+    }
+
+    /// When the first genuine RTT sample was recorded, if ever
+    pub fn first_sample_time(&self) -> Option<Instant> {
+        self.first_sample_time
+    }
+
+    /// Negotiated peer max ack delay, if any
+    pub fn max_ack_delay(&self) -> Option<Duration> {
+        self.max_ack_delay.get()
+    }
+
+    /// Record the peer's advertised maximum ack delay, e.g. from transport parameters or a
+    /// received ACK_FREQUENCY frame
+    pub fn set_max_ack_delay(&mut self, max_ack_delay: Duration) {
+        self.max_ack_delay.set(max_ack_delay);
+    }
+
+    pub fn update(&mut self, now: Instant, ack_delay: Duration, rtt: Duration) {
+        let ack_delay = match self.max_ack_delay.get() {
+            Some(max) => cmp::min(ack_delay, max),
+            None => ack_delay,
+        };
         self.latest = rtt;
+        if self.first_sample_time.is_none() {
+            // Genuine first sample: start fresh from it rather than trusting whatever `min`,
+            // `var` and `smoothed` were seeded with, so an unlucky first RTT can't permanently
+            // poison `min`.
+            self.first_sample_time = Some(now);
+            self.min = self.latest;
+            self.var = self.latest / 2;
+            self.smoothed = Some(self.latest);
+            return;
+        }
         // min_rtt ignores ack delay.
         self.min = cmp::min(self.min, self.latest);
         // Based on RFC6298.
-        if let Some(smoothed) = self.smoothed {
-            let adjusted_rtt = if self.min + ack_delay < self.latest {
-                self.latest - ack_delay
-            } else {
-                self.latest
-            };
-            let var_sample = if smoothed > adjusted_rtt {
-                smoothed - adjusted_rtt
-            } else {
-                adjusted_rtt - smoothed
-            };
-            self.var = (3 * self.var + var_sample) / 4;
-            self.smoothed = Some((7 * smoothed + adjusted_rtt) / 8);
+        let smoothed = self.smoothed.expect("smoothed is set on the first sample");
+        let adjusted_rtt = if self.min + ack_delay < self.latest {
+            self.latest - ack_delay
         } else {
-            self.smoothed = Some(self.latest);
-            self.var = self.latest / 2;
-            self.min = self.latest;
-        }
+            self.latest
+        };
+        let var_sample = if smoothed > adjusted_rtt {
+            smoothed - adjusted_rtt
+        } else {
+            adjusted_rtt - smoothed
+        };
+        self.var = (3 * self.var + var_sample) / 4;
+        self.smoothed = Some((7 * smoothed + adjusted_rtt) / 8);
     }
 
     pub fn get(&self) -> Duration {
         self.smoothed.unwrap_or(self.latest)
     }
 
+    /// Smoothed RTT to persist in a resumption token, clamped to a sane ceiling
+    ///
+    /// A handshake that completes under heavy loss can produce a wildly inflated smoothed RTT;
+    /// seeding a future handshake's PTO from that value would make it far too conservative and
+    /// could stall reconnection. We clamp to whichever is smaller of a small multiple of the
+    /// observed minimum and a fixed absolute ceiling.
+    pub fn to_resumption(&self) -> Duration {
+        let rtt = self.get();
+        let relative_ceiling = self.min * RESUMPTION_RTT_MIN_MULTIPLIER;
+        cmp::min(cmp::min(rtt, relative_ceiling), RESUMPTION_RTT_MAX)
+    }
+
+    /// Build an estimator seeded from a previously persisted [`to_resumption`] value
+    ///
+    /// Unlike [`new`](Self::new), which seeds from a blind default, this is trusted history from
+    /// an earlier connection to the same peer: `is_guessed()` reports `false` immediately, so
+    /// `pto_base`/`conservative` don't apply their extra no-sample-yet backoff to it.
+    ///
+    /// [`to_resumption`]: Self::to_resumption
+    pub fn from_resumption(rtt: Duration) -> Self {
+        let rtt = cmp::min(rtt, RESUMPTION_RTT_MAX);
+        Self {
+            trusted_seed: true,
+            ..Self::new(rtt)
+        }
+    }
+
     /// Conservative estimate of RTT
     ///
     /// Takes the maximum of smoothed and latest RTT, as recommended
-    /// in 6.1.2 of the recovery spec (draft 29).
+    /// in 6.1.2 of the recovery spec (draft 29). Doubled while [`is_guessed`](Self::is_guessed),
+    /// since `latest`/`smoothed` are then still just the blind `initial_rtt` guess; an estimator
+    /// seeded by `from_resumption` is not doubled.
     pub fn conservative(&self) -> Duration {
-        self.get().max(self.latest)
+        let estimate = self.get().max(self.latest);
+        if self.is_guessed() {
+            estimate * 2
+        } else {
+            estimate
+        }
     }
 
     pub fn pto_base(&self) -> Duration {
-        self.get() + cmp::max(4 * self.var, TIMER_GRANULARITY)
+        let pto = self.get() + cmp::max(4 * self.var, TIMER_GRANULARITY);
+        if self.is_guessed() {
+            pto * 2
+        } else {
+            pto
+        }
+    }
+
+    /// PTO for the application data packet number space
+    ///
+    /// Unlike `pto_base`, this adds the peer's advertised maximum ack delay, since the peer is
+    /// permitted to hold application-space ACKs for up to that long before sending them.
+    pub fn pto_base_app(&self) -> Duration {
+        self.pto_base() + self.max_ack_delay.get().unwrap_or_default()
+    }
+
+    /// Diff the current RTT fields against the last-emitted snapshot
+    ///
+    /// Returns `None`, doing nothing else, if nothing changed. Otherwise updates the snapshot and
+    /// returns an update with only the changed fields set, so qlog output stays compact.
+    fn qlog_update(&mut self) -> Option<RttMetricsUpdate> {
+        let current = (self.latest, self.smoothed, self.min, self.var);
+        if self.last_emitted == Some(current) {
+            return None;
+        }
+        let prev = self.last_emitted;
+        self.last_emitted = Some(current);
+        Some(RttMetricsUpdate {
+            latest_rtt: (prev.map(|p| p.0) != Some(self.latest)).then_some(self.latest),
+            smoothed_rtt: (prev.map(|p| p.1) != Some(self.smoothed))
+                .then_some(self.smoothed)
+                .flatten(),
+            min_rtt: (prev.map(|p| p.2) != Some(self.min)).then_some(self.min),
+            rtt_variance: (prev.map(|p| p.3) != Some(self.var)).then_some(self.var),
+        })
+    }
+}
+
+/// Desired ACK rate for a path, as would be communicated via an ACK_FREQUENCY frame
+///
+/// Derived from the current smoothed RTT and congestion window: a larger window tolerates larger
+/// ack-eliciting thresholds before losing timely congestion feedback, and a larger RTT tolerates a
+/// larger requested max ack delay.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AckRate {
+    /// Number of ack-eliciting packets the peer should receive before sending an ACK
+    pub ack_eliciting_threshold: u64,
+    /// Maximum delay the peer should wait before sending an ACK in response to this path's traffic
+    pub request_max_ack_delay: Duration,
+    /// Maximum reordering, in packet numbers, tolerated before the peer should ACK immediately
+    pub reordering_threshold: u64,
+}
+
+impl AckRate {
+    fn compute(rtt: Duration, congestion_window: u64, mtu: u16) -> Self {
+        let packets_per_rtt = cmp::max(congestion_window / mtu as u64, 1);
+        // Ask for roughly one ACK per quarter-window of packets, as recommended by the ACK
+        // Frequency draft, while never setting the threshold below one packet.
+        let ack_eliciting_threshold = cmp::max(packets_per_rtt / 4, 1);
+        let request_max_ack_delay = cmp::min(
+            cmp::max(rtt / 4, MIN_REQUESTED_ACK_DELAY),
+            MAX_REQUESTED_ACK_DELAY,
+        );
+        Self {
+            ack_eliciting_threshold,
+            request_max_ack_delay,
+            reordering_threshold: ack_eliciting_threshold,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mtud_search_converges_and_rearms() {
+        let now = Instant::now();
+        let mut mtud = Mtud::new(1452, now);
+        assert_eq!(mtud.current_mtu(), MIN_MTU);
+
+        // Binary search should converge to the ceiling if every probe succeeds.
+        let mut pn = 0;
+        while let Some((size, probe_pn)) = mtud.poll_transmit(now, pn) {
+            assert!(size > mtud.current_mtu());
+            assert!(size <= mtud.ceiling());
+            mtud.on_probe_acked(probe_pn, now);
+            pn += 1;
+            assert!(
+                pn < 64,
+                "search should converge well before this many probes"
+            );
+        }
+        assert_eq!(mtud.current_mtu(), mtud.ceiling());
+        assert_eq!(mtud.state, MtudState::SearchComplete);
+
+        // The re-arm deadline must be pushed into the future on convergence, not left stale at
+        // whatever `now` was when the search started - otherwise the very next `maybe_rearm`
+        // call would immediately restart probing.
+        assert!(mtud.next_search_at > now);
+        mtud.maybe_rearm(now);
+        assert_eq!(
+            mtud.state,
+            MtudState::SearchComplete,
+            "must not re-arm before the discovery interval elapses"
+        );
+
+        // Once the interval has actually elapsed, re-arming is expected to reset the state.
+        mtud.maybe_rearm(now + MTU_DISCOVERY_INTERVAL);
+        assert_eq!(mtud.state, MtudState::Base);
+    }
+
+    #[test]
+    fn mtud_poll_transmit_short_circuit_rearms_deadline() {
+        let now = Instant::now();
+        let mut mtud = Mtud::new(1452, now);
+
+        // Converge fully via `on_probe_acked`, which is already known to set `next_search_at`.
+        let mut pn = 0;
+        while let Some((_, probe_pn)) = mtud.poll_transmit(now, pn) {
+            mtud.on_probe_acked(probe_pn, now);
+            pn += 1;
+            assert!(
+                pn < 64,
+                "search should converge well before this many probes"
+            );
+        }
+        assert_eq!(mtud.state, MtudState::SearchComplete);
+
+        // Re-arming exactly at the deadline flips back to `Base`; the subsequent `poll_transmit`
+        // call then hits the `search_high <= current_mtu` short-circuit (since we're already at
+        // the ceiling), which must *also* push `next_search_at` into the future.
+        mtud.maybe_rearm(now + MTU_DISCOVERY_INTERVAL);
+        assert_eq!(mtud.state, MtudState::Base);
+        assert!(mtud
+            .poll_transmit(now + MTU_DISCOVERY_INTERVAL, pn)
+            .is_none());
+        assert_eq!(mtud.state, MtudState::SearchComplete);
+
+        // A `maybe_rearm` call shortly after must NOT flip state again: without the fix,
+        // `next_search_at` would still be stuck at `now + MTU_DISCOVERY_INTERVAL` (already in the
+        // past relative to this call), so it would incorrectly reset to `Base` every time,
+        // forever, rather than waiting out a fresh interval.
+        mtud.maybe_rearm(now + MTU_DISCOVERY_INTERVAL + Duration::from_millis(1));
+        assert_eq!(
+            mtud.state,
+            MtudState::SearchComplete,
+            "must wait out a fresh interval, not re-arm immediately"
+        );
+    }
+
+    #[test]
+    fn mtud_probe_loss_narrows_search() {
+        let now = Instant::now();
+        let mut mtud = Mtud::new(1452, now);
+        let (first_probe_size, pn) = mtud.poll_transmit(now, 0).unwrap();
+        mtud.on_probe_lost(pn, now);
+        // Losing a probe must narrow the window, not be treated as a congestion signal.
+        assert_eq!(mtud.current_mtu(), MIN_MTU);
+        assert!(mtud.ceiling() >= first_probe_size);
+
+        if let Some((next_probe_size, _)) = mtud.poll_transmit(now, 1) {
+            assert!(next_probe_size < first_probe_size);
+        }
+    }
+
+    #[test]
+    fn mtud_black_hole_resets_to_min() {
+        let now = Instant::now();
+        let mut mtud = Mtud::new(1452, now);
+        let (_, pn) = mtud.poll_transmit(now, 0).unwrap();
+        mtud.on_probe_acked(pn, now);
+        assert!(mtud.current_mtu() > MIN_MTU);
+
+        mtud.black_hole_detected(now);
+        assert_eq!(mtud.current_mtu(), MIN_MTU);
+        // Must not probe again until the search is re-armed.
+        assert!(mtud.poll_transmit(now, 1).is_none());
+        mtud.maybe_rearm(now + MTU_DISCOVERY_INTERVAL);
+        assert!(mtud
+            .poll_transmit(now + MTU_DISCOVERY_INTERVAL, 1)
+            .is_some());
+    }
+
+    #[test]
+    fn mtud_persistent_loss_triggers_black_hole() {
+        let now = Instant::now();
+        let mut mtud = Mtud::new(1452, now);
+        let (_, pn) = mtud.poll_transmit(now, 0).unwrap();
+        mtud.on_probe_acked(pn, now);
+        assert!(mtud.current_mtu() > MIN_MTU);
+
+        assert!(!mtud.on_non_probe_lost(now, 3));
+        assert!(!mtud.on_non_probe_lost(now, 3));
+        assert!(mtud.on_non_probe_lost(now, 3));
+        assert_eq!(mtud.current_mtu(), MIN_MTU);
+    }
+
+    #[test]
+    fn ecn_validation_succeeds_on_matching_counts() {
+        let mut ecn = EcnValidation::new();
+        assert!(ecn.is_capable());
+        ecn.on_packet_sent();
+        ecn.on_packet_sent();
+        ecn.on_ack(2, 2, 0);
+        assert!(ecn.is_capable());
+        assert_eq!(ecn.state, EcnState::Capable);
+    }
+
+    #[test]
+    fn ecn_validation_fails_on_undercount() {
+        let mut ecn = EcnValidation::new();
+        ecn.on_packet_sent();
+        ecn.on_packet_sent();
+        // The peer reports fewer newly-marked packets than we know it just acknowledged: a
+        // middlebox is erasing or remapping the codepoint.
+        ecn.on_ack(2, 1, 0);
+        assert!(!ecn.is_capable());
+    }
+
+    #[test]
+    fn ecn_validation_fails_after_silent_testing_window() {
+        let mut ecn = EcnValidation::new();
+        for _ in 0..ECN_TESTING_WINDOW {
+            ecn.on_packet_sent();
+        }
+        assert!(ecn.is_capable());
+        ecn.on_testing_window_elapsed();
+        assert!(!ecn.is_capable());
+    }
+
+    #[test]
+    fn ecn_validation_stays_failed_once_failed() {
+        let mut ecn = EcnValidation::new();
+        ecn.on_packet_sent();
+        ecn.on_ack(1, 0, 0);
+        assert!(!ecn.is_capable());
+        // A later well-formed report must not resurrect a path we've already given up on.
+        ecn.on_packet_sent();
+        ecn.on_ack(1, 10, 0);
+        assert!(!ecn.is_capable());
+    }
+
+    #[test]
+    fn rtt_estimator_new_is_guessed_until_first_sample() {
+        let mut rtt = RttEstimator::new(Duration::from_millis(100));
+        assert!(rtt.is_guessed());
+        rtt.update(Instant::now(), Duration::ZERO, Duration::from_millis(100));
+        assert!(!rtt.is_guessed());
+    }
+
+    #[test]
+    fn rtt_estimator_resumed_is_not_guessed() {
+        // A seed from a previous connection's resumption token is trusted history, not a blind
+        // guess, even before this connection has a real sample of its own.
+        let rtt = RttEstimator::from_resumption(Duration::from_millis(80));
+        assert!(!rtt.is_guessed());
+        assert!(rtt.pto_base() < RttEstimator::new(Duration::from_millis(80)).pto_base());
+    }
+
+    #[test]
+    fn rtt_estimator_to_resumption_clamps_to_relative_multiplier() {
+        let mut rtt = RttEstimator::new(Duration::from_millis(1));
+        rtt.min = Duration::from_millis(10);
+        rtt.smoothed = Some(Duration::from_millis(100));
+        // 3 * min (30ms) is tighter than both the raw rtt (100ms) and the absolute ceiling.
+        assert_eq!(rtt.to_resumption(), Duration::from_millis(30));
+    }
+
+    #[test]
+    fn rtt_estimator_to_resumption_clamps_to_absolute_ceiling() {
+        let mut rtt = RttEstimator::new(Duration::from_millis(1));
+        rtt.min = Duration::from_millis(200);
+        rtt.smoothed = Some(Duration::from_millis(900));
+        // 3 * min (600ms) is looser than the absolute ceiling, so the ceiling wins instead.
+        assert_eq!(rtt.to_resumption(), RESUMPTION_RTT_MAX);
+    }
+
+    #[test]
+    fn rtt_estimator_from_resumption_clamps_seed_to_absolute_ceiling() {
+        let rtt = RttEstimator::from_resumption(Duration::from_millis(900));
+        assert_eq!(rtt.get(), RESUMPTION_RTT_MAX);
+        assert!(!rtt.is_guessed());
+    }
+
+    #[test]
+    fn rtt_estimator_qlog_update_reports_everything_on_first_call() {
+        let mut rtt = RttEstimator::new(Duration::from_millis(100));
+        rtt.update(Instant::now(), Duration::ZERO, Duration::from_millis(100));
+        let update = rtt
+            .qlog_update()
+            .expect("first call always has something to report");
+        assert_eq!(update.latest_rtt, Some(rtt.latest));
+        assert_eq!(update.smoothed_rtt, rtt.smoothed);
+        assert_eq!(update.min_rtt, Some(rtt.min));
+        assert_eq!(update.rtt_variance, Some(rtt.var));
+    }
+
+    #[test]
+    fn rtt_estimator_qlog_update_omits_everything_when_nothing_changed() {
+        let mut rtt = RttEstimator::new(Duration::from_millis(100));
+        rtt.update(Instant::now(), Duration::ZERO, Duration::from_millis(100));
+        assert!(rtt.qlog_update().is_some());
+        // Nothing has changed since the last emission, so there's nothing new to report.
+        assert_eq!(rtt.qlog_update(), None);
+    }
+
+    #[test]
+    fn rtt_estimator_qlog_update_reports_only_the_field_that_changed() {
+        let mut rtt = RttEstimator::new(Duration::from_millis(100));
+        rtt.update(Instant::now(), Duration::ZERO, Duration::from_millis(100));
+        assert!(rtt.qlog_update().is_some());
+
+        // Perturb only `latest`, bypassing `update` so the other three fields are untouched.
+        rtt.latest = Duration::from_millis(150);
+        let update = rtt
+            .qlog_update()
+            .expect("latest changed, so there's something to report");
+        assert_eq!(
+            update,
+            RttMetricsUpdate {
+                latest_rtt: Some(Duration::from_millis(150)),
+                ..RttMetricsUpdate::default()
+            }
+        );
+    }
+
+    #[test]
+    fn rtt_estimator_update_clamps_ack_delay_to_peer_max() {
+        let now = Instant::now();
+        let mut rtt = RttEstimator::new(Duration::from_millis(100));
+        rtt.update(now, Duration::ZERO, Duration::from_millis(100));
+        rtt.set_max_ack_delay(Duration::from_millis(10));
+
+        // The peer claims a much larger ack delay than it negotiated; it must be clamped to the
+        // negotiated maximum before being subtracted out of the sample.
+        rtt.update(now, Duration::from_millis(100), Duration::from_millis(150));
+
+        // With the ack delay clamped to 10ms: adjusted_rtt = 150ms - 10ms = 140ms, so
+        // smoothed = (7 * 100ms + 140ms) / 8 = 105ms. Without the clamp it would instead use the
+        // unclamped 100ms ack delay, which (since min + ack_delay >= latest) leaves the sample
+        // unadjusted and yields 106.25ms instead.
+        assert_eq!(rtt.smoothed, Some(Duration::from_millis(105)));
+    }
+
+    #[test]
+    fn ack_rate_compute_clamps_requested_delay_to_min() {
+        let rate = AckRate::compute(Duration::ZERO, 12_000, 1200);
+        assert_eq!(rate.request_max_ack_delay, MIN_REQUESTED_ACK_DELAY);
+    }
+
+    #[test]
+    fn ack_rate_compute_clamps_requested_delay_to_max() {
+        let rate = AckRate::compute(Duration::from_millis(200), 12_000, 1200);
+        assert_eq!(rate.request_max_ack_delay, MAX_REQUESTED_ACK_DELAY);
+    }
+
+    #[test]
+    fn ack_rate_compute_never_asks_for_fewer_than_one_packet_per_ack() {
+        let rate = AckRate::compute(Duration::from_millis(50), 1000, 1452);
+        assert_eq!(rate.ack_eliciting_threshold, 1);
+        assert_eq!(rate.reordering_threshold, 1);
     }
 }